@@ -6,9 +6,79 @@ use std::env;
 mod ir {
 
     use std::collections::HashMap;
+    use std::fmt;
     use std::fs::File;
 
-    type Ordinal = u64;
+    /// A FIDL method/table ordinal. Zero is never a valid ordinal, so the
+    /// `Deserialize` impl rejects it with a clear error rather than letting
+    /// malformed IR through.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Ordinal(u64);
+
+    impl<'de> serde::Deserialize<'de> for Ordinal {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let value = u64::deserialize(deserializer)?;
+            if value == 0 {
+                return Err(serde::de::Error::custom("ordinal must be non-zero"));
+            }
+            Ok(Ordinal(value))
+        }
+    }
+
+    impl fmt::Display for Ordinal {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl From<u64> for Ordinal {
+        fn from(value: u64) -> Self {
+            Ordinal(value)
+        }
+    }
+
+    /// A transparent wrapper around a library's dotted name.
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(transparent)]
+    struct LibraryName(String);
+
+    impl LibraryName {
+        fn as_str(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl fmt::Display for LibraryName {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl From<String> for LibraryName {
+        fn from(value: String) -> Self {
+            LibraryName(value)
+        }
+    }
+
+    /// A transparent wrapper around a declaration's name.
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(transparent)]
+    struct DeclarationName(String);
+
+    impl fmt::Display for DeclarationName {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl From<String> for DeclarationName {
+        fn from(value: String) -> Self {
+            DeclarationName(value)
+        }
+    }
 
     #[derive(Deserialize, Debug)]
     #[serde(rename_all = "lowercase")]
@@ -34,7 +104,7 @@ mod ir {
         Profile,
     }
 
-    #[derive(Deserialize, Debug)]
+    #[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
     #[serde(rename_all = "lowercase")]
     enum DeclarationType {
         Const,
@@ -99,10 +169,62 @@ mod ir {
         Identifier { identifier: String },
         Literal { literal: Literal },
     }
+
+    impl Constant {
+        /// The constant rendered as a string, matching the source spelling of
+        /// a literal or the raw identifier of a reference.
+        fn value(&self) -> &str {
+            match self {
+                Constant::Identifier { identifier } => identifier,
+                Constant::Literal { literal } => match literal {
+                    Literal::String { value } => value,
+                    Literal::Numeric { value } => value,
+                    Literal::True {} => "true",
+                    Literal::False {} => "false",
+                    Literal::Default {} => "default",
+                },
+            }
+        }
+
+        /// Parse the constant's [`value`](Constant::value) as a signed integer,
+        /// honouring an optional leading `-` and `0x` hex prefix.
+        fn integer_value(&self) -> Result<i128, std::num::ParseIntError> {
+            let raw = self.value().trim();
+            let (negative, rest) = match raw.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, raw),
+            };
+            let magnitude = match rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+                Some(hex) => i128::from_str_radix(hex, 16)?,
+                None => rest.parse::<i128>()?,
+            };
+            Ok(if negative { -magnitude } else { magnitude })
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct AttributeArgument {
+        name: String,
+        value: Constant,
+    }
+
     #[derive(Deserialize, Debug)]
     struct Attribute {
         name: String,
-        value: String,
+        arguments: Vec<AttributeArgument>,
+    }
+
+    /// Collect the string values of every argument of the first attribute
+    /// named `name`, if present.
+    fn get_attribute(attrs: &Option<Vec<Attribute>>, name: &str) -> Option<Vec<String>> {
+        attrs.as_ref().and_then(|attrs| {
+            attrs.iter().find(|a| a.name == name).map(|a| {
+                a.arguments
+                    .iter()
+                    .map(|arg| arg.value.value().to_owned())
+                    .collect()
+            })
+        })
     }
 
     #[derive(Deserialize, Debug)]
@@ -218,20 +340,20 @@ mod ir {
 
     #[derive(Deserialize, Debug)]
     struct Declaration {
-        name: String,
+        name: DeclarationName,
         maybe_attributes: Option<Vec<Attribute>>,
     }
 
     #[derive(Deserialize, Debug)]
     struct LibraryDependency {
-        name: String,
+        name: LibraryName,
         declarations: HashMap<String, DeclarationType>,
     }
 
     #[derive(Deserialize, Debug)]
     pub struct Library {
         version: String,
-        name: String,
+        name: LibraryName,
         const_declarations: Vec<Const>,
         enum_declarations: Vec<Enum>,
         interface_declarations: Vec<Protocol>,
@@ -244,8 +366,1417 @@ mod ir {
         library_dependencies: Vec<LibraryDependency>,
     }
 
+    /// Read an IR file, migrating it forward from whatever schema version it
+    /// was written in to the canonical [`Library`] model.
     pub fn read_ir(filepath: &str) -> Library {
-        serde_json::from_reader(File::open(filepath).expect("file not found")).expect("json error")
+        let ir: serde_json::Value =
+            serde_json::from_reader(File::open(filepath).expect("file not found"))
+                .expect("json error");
+        serde_json::from_value(migrate(ir)).expect("json error")
+    }
+
+    /// Dispatch on the top-level `version` field and run the migration chain
+    /// up to the canonical schema. Each schema module performs exactly one
+    /// `vN -> vN+1` step, so supporting a future version means adding one
+    /// module and one arm here.
+    fn migrate(ir: serde_json::Value) -> serde_json::Value {
+        let version = ir
+            .get("version")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("");
+        match version {
+            // The original fidlc IR attached a single `value` string per
+            // attribute; bring it up to the `arguments` list v2 expects.
+            "0.0.1" => v2::up_convert(v1::up_convert(ir)),
+            _ => ir,
+        }
+    }
+
+    /// The first released IR schema: attributes carry a single `value`.
+    mod v1 {
+        use serde_json::{json, Value};
+
+        /// Convert a v1 document to the v2 schema by rewriting every
+        /// attribute's `value` string into a single-argument list.
+        pub fn up_convert(mut ir: Value) -> Value {
+            convert(&mut ir);
+            ir
+        }
+
+        fn convert(v: &mut Value) {
+            match v {
+                Value::Object(map) => {
+                    if let Some(Value::Array(attrs)) = map.get_mut("maybe_attributes") {
+                        for attr in attrs.iter_mut() {
+                            convert_attribute(attr);
+                        }
+                    }
+                    for child in map.values_mut() {
+                        convert(child);
+                    }
+                }
+                Value::Array(items) => {
+                    for item in items.iter_mut() {
+                        convert(item);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn convert_attribute(attr: &mut Value) {
+            if let Value::Object(map) = attr {
+                if map.contains_key("arguments") {
+                    return;
+                }
+                let value = map.remove("value");
+                let arguments = match value {
+                    Some(value) => {
+                        let text = value.as_str().unwrap_or("").to_owned();
+                        json!([{
+                            "name": "value",
+                            "value": {
+                                "kind": "literal",
+                                "literal": { "kind": "string", "value": text },
+                            },
+                        }])
+                    }
+                    None => json!([]),
+                };
+                map.insert("arguments".to_owned(), arguments);
+            }
+        }
+    }
+
+    /// The current IR schema: attributes carry an `arguments` list. This is
+    /// the canonical model the rest of the crate deserializes into, so its
+    /// up-conversion is the identity.
+    mod v2 {
+        use serde_json::Value;
+
+        pub fn up_convert(ir: Value) -> Value {
+            ir
+        }
+    }
+
+    pub mod resolver {
+        //! Resolves the raw compound identifiers carried by
+        //! [`Type::Identifier`] (for example `fuchsia.io/NodeInfo`) into the
+        //! declaring library, its [`DeclarationType`], and a canonical Rust
+        //! module path such as `fuchsia_io::NodeInfo`.
+        use super::*;
+        use regex::Regex;
+        use std::fmt;
+
+        /// A compound identifier split into its library-qualified part and an
+        /// optional trailing member name.
+        #[derive(Debug)]
+        pub struct CompoundIdentifier {
+            pub library: String,
+            pub member: Option<String>,
+        }
+
+        /// The fully resolved form of a compound identifier.
+        #[derive(Debug)]
+        pub struct Resolved {
+            pub library: String,
+            pub declaration_type: DeclarationType,
+            pub module_path: String,
+        }
+
+        #[derive(Debug)]
+        pub enum ResolveError {
+            /// The identifier did not match the compound-identifier grammar.
+            Malformed(String),
+            /// The identifier did not name a known declaration.
+            Unknown(String),
+        }
+
+        impl fmt::Display for ResolveError {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    ResolveError::Malformed(id) => write!(f, "malformed identifier `{}`", id),
+                    ResolveError::Unknown(id) => write!(f, "unknown identifier `{}`", id),
+                }
+            }
+        }
+
+        impl std::error::Error for ResolveError {}
+
+        /// Resolves identifiers against a single [`Library`] and its
+        /// dependencies.
+        pub struct Resolver<'a> {
+            lib: &'a Library,
+            pattern: Regex,
+        }
+
+        impl<'a> Resolver<'a> {
+            pub fn new(lib: &'a Library) -> Self {
+                // `([_A-Za-z][_A-Za-z0-9]*)(/[_A-Za-z][_A-Za-z0-9]*)?`, with
+                // dots permitted in the library part so dotted library names
+                // such as `fuchsia.io` parse as a single segment.
+                let pattern =
+                    Regex::new(r"^([_A-Za-z][_A-Za-z0-9.]*)(?:/([_A-Za-z][_A-Za-z0-9]*))?$")
+                        .expect("resolver regex");
+                Resolver { lib, pattern }
+            }
+
+            /// Split a raw identifier into its library and member parts.
+            pub fn parse(&self, identifier: &str) -> Result<CompoundIdentifier, ResolveError> {
+                let caps = self
+                    .pattern
+                    .captures(identifier)
+                    .ok_or_else(|| ResolveError::Malformed(identifier.to_owned()))?;
+                Ok(CompoundIdentifier {
+                    library: caps[1].to_owned(),
+                    member: caps.get(2).map(|m| m.as_str().to_owned()),
+                })
+            }
+
+            /// Resolve a raw identifier to its declaring library and a
+            /// canonical Rust module path.
+            pub fn resolve(&self, identifier: &str) -> Result<Resolved, ResolveError> {
+                let parsed = self.parse(identifier)?;
+                // Validate that the identifier names a known declaration.
+                let declaration_type = self.lookup(identifier, &parsed)?;
+                Ok(Resolved {
+                    library: parsed.library.clone(),
+                    declaration_type,
+                    module_path: module_path(&parsed),
+                })
+            }
+
+            /// Resolve an identifier to the Rust path codegen should emit: a
+            /// bare member name for declarations in this library, a
+            /// library-qualified path (e.g. `fuchsia_io::NodeInfo`) for
+            /// imported ones. Identifiers that resolve to nothing fall back to
+            /// their bare member name.
+            pub fn rust_path(&self, identifier: &str) -> String {
+                match self.parse(identifier) {
+                    Ok(parsed) if parsed.library == self.lib.name.as_str() => {
+                        member_or(parsed.member, identifier)
+                    }
+                    Ok(parsed) => match self.resolve(identifier) {
+                        Ok(resolved) => resolved.module_path,
+                        Err(_) => member_or(parsed.member, identifier),
+                    },
+                    Err(_) => member_or(None, identifier),
+                }
+            }
+
+            /// Find the [`DeclarationType`] for `identifier`, consulting the
+            /// local library first and then its dependencies.
+            fn lookup(
+                &self,
+                identifier: &str,
+                parsed: &CompoundIdentifier,
+            ) -> Result<DeclarationType, ResolveError> {
+                if parsed.library == self.lib.name.as_str() {
+                    if let Some(ty) = self.lib.declarations.get(identifier) {
+                        return Ok(*ty);
+                    }
+                }
+                for dep in &self.lib.library_dependencies {
+                    if dep.name.as_str() == parsed.library {
+                        if let Some(ty) = dep.declarations.get(identifier) {
+                            return Ok(*ty);
+                        }
+                    }
+                }
+                Err(ResolveError::Unknown(identifier.to_owned()))
+            }
+        }
+
+        /// The member name of a parsed identifier, falling back to the trailing
+        /// segment of the raw identifier when there is none.
+        fn member_or(member: Option<String>, identifier: &str) -> String {
+            member.unwrap_or_else(|| identifier.rsplit('/').next().unwrap_or(identifier).to_owned())
+        }
+
+        /// Turn a parsed identifier into a canonical Rust module path, mapping
+        /// the dots in a library name onto `_`.
+        fn module_path(parsed: &CompoundIdentifier) -> String {
+            let module = parsed.library.replace('.', "_");
+            match &parsed.member {
+                Some(member) => format!("{}::{}", module, member),
+                None => module,
+            }
+        }
+    }
+
+    pub mod encoding {
+        //! Generates FIDL wire-format `encode`/`decode` methods for structs
+        //! and unions from the layout metadata (`offset`, `size`,
+        //! `alignment`, `max_out_of_line`, `max_handles`) the IR records for
+        //! every member.
+        use super::*;
+        use std::fmt::Write;
+
+        /// Emit `encode`/`decode` impls for every struct and union in `lib`,
+        /// preceded by the shared `DecodeError` the generated code returns.
+        pub fn generate(lib: &Library) -> String {
+            let mut out = String::new();
+            emit_preamble(&mut out);
+            for name in &lib.declaration_order {
+                match lib.declarations.get(name) {
+                    Some(DeclarationType::Struct) => {
+                        if let Some(s) = lib.struct_declarations.iter().find(|s| &s.name == name) {
+                            emit_struct_codec(&mut out, s);
+                        }
+                    }
+                    Some(DeclarationType::Union) | Some(DeclarationType::XUnion) => {
+                        if let Some(u) = lib.union_declarations.iter().find(|u| &u.name == name) {
+                            emit_union_codec(&mut out, u);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            out
+        }
+
+        fn emit_preamble(out: &mut String) {
+            out.push_str(
+                "#[derive(Debug)]\n\
+                 pub enum DecodeError {\n\
+                 \x20   BufferTooShort,\n\
+                 \x20   TrailingBytes,\n\
+                 \x20   UnknownOrdinal(u64),\n\
+                 }\n\n",
+            );
+        }
+
+        fn emit_struct_codec(out: &mut String, s: &Struct) {
+            let name = local_name(&s.name);
+            let has_ool = s.members.iter().any(|m| is_out_of_line(&m.r#type));
+            writeln!(out, "impl {} {{", name).unwrap();
+            writeln!(out, "    /// Inline size of this struct on the wire.").unwrap();
+            writeln!(out, "    pub const SIZE: usize = {};", s.size).unwrap();
+            writeln!(
+                out,
+                "    pub const MAX_HANDLES: usize = {};",
+                s.max_handles.unwrap_or(0)
+            )
+            .unwrap();
+            writeln!(out, "    pub const MAX_OUT_OF_LINE: usize = {};", s.max_out_of_line).unwrap();
+            out.push('\n');
+
+            // encode: write every field into the inline region, then append
+            // any out-of-line content 8-byte aligned past it.
+            writeln!(out, "    pub fn encode(&self, buf: &mut Vec<u8>) {{").unwrap();
+            writeln!(out, "        let base = buf.len();").unwrap();
+            writeln!(out, "        buf.resize(base + Self::SIZE, 0);").unwrap();
+            for m in &s.members {
+                emit_encode_field(out, &m.name, &m.r#type, m.offset);
+            }
+            writeln!(out, "    }}").unwrap();
+            out.push('\n');
+
+            // decode: read the inline region, then consume out-of-line blocks
+            // with `ool`, and finally require the buffer to be exactly as long
+            // as everything consumed.
+            writeln!(
+                out,
+                "    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {{"
+            )
+            .unwrap();
+            writeln!(out, "        if buf.len() < Self::SIZE {{").unwrap();
+            writeln!(out, "            return Err(DecodeError::BufferTooShort);").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(
+                out,
+                "        let {}ool = Self::SIZE;",
+                if has_ool { "mut " } else { "" }
+            )
+            .unwrap();
+            for m in &s.members {
+                emit_decode_field(out, &m.name, &m.r#type, m.offset);
+            }
+            writeln!(out, "        if ool != buf.len() {{").unwrap();
+            writeln!(out, "            return Err(DecodeError::TrailingBytes);").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(out, "        Ok(Self {{").unwrap();
+            for m in &s.members {
+                writeln!(out, "            {},", m.name).unwrap();
+            }
+            writeln!(out, "        }})").unwrap();
+            writeln!(out, "    }}").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        fn emit_union_codec(out: &mut String, u: &Union) {
+            let name = local_name(&u.name);
+            writeln!(out, "impl {} {{", name).unwrap();
+            writeln!(out, "    pub const SIZE: usize = {};", u.size).unwrap();
+            writeln!(
+                out,
+                "    pub const MAX_HANDLES: usize = {};",
+                u.max_handles.unwrap_or(0)
+            )
+            .unwrap();
+            out.push('\n');
+
+            // encode: tag (u32) at offset 0 then the active variant's payload
+            // in the 8-byte-aligned out-of-line region.
+            writeln!(out, "    pub fn encode(&self, buf: &mut Vec<u8>) {{").unwrap();
+            writeln!(out, "        let base = buf.len();").unwrap();
+            writeln!(out, "        buf.resize(base + Self::SIZE, 0);").unwrap();
+            writeln!(out, "        match self {{").unwrap();
+            for (tag, m) in u.members.iter().enumerate() {
+                writeln!(out, "            {}::{}(value) => {{", name, m.name).unwrap();
+                writeln!(
+                    out,
+                    "                buf[base..base + 4].copy_from_slice(&{}u32.to_le_bytes());",
+                    tag
+                )
+                .unwrap();
+                emit_payload_encode(out, &m.r#type, "                ");
+                writeln!(out, "            }}").unwrap();
+            }
+            writeln!(out, "        }}").unwrap();
+            writeln!(out, "    }}").unwrap();
+            out.push('\n');
+
+            // decode: read the tag, decode the matching variant's payload from
+            // the out-of-line region, then require an exact-length buffer.
+            writeln!(
+                out,
+                "    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {{"
+            )
+            .unwrap();
+            writeln!(out, "        if buf.len() < Self::SIZE {{").unwrap();
+            writeln!(out, "            return Err(DecodeError::BufferTooShort);").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(
+                out,
+                "        let tag = u32::from_le_bytes(buf[0..4].try_into().unwrap());"
+            )
+            .unwrap();
+            writeln!(out, "        let mut ool = Self::SIZE;").unwrap();
+            writeln!(out, "        let value = match tag {{").unwrap();
+            for (tag, m) in u.members.iter().enumerate() {
+                writeln!(out, "            {} => {{", tag).unwrap();
+                emit_payload_decode(out, &m.r#type, "                ");
+                writeln!(out, "                {}::{}(payload)", name, m.name).unwrap();
+                writeln!(out, "            }}").unwrap();
+            }
+            writeln!(
+                out,
+                "            other => return Err(DecodeError::UnknownOrdinal(other as u64)),"
+            )
+            .unwrap();
+            writeln!(out, "        }};").unwrap();
+            writeln!(out, "        if ool != buf.len() {{").unwrap();
+            writeln!(out, "            return Err(DecodeError::TrailingBytes);").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(out, "        Ok(value)").unwrap();
+            writeln!(out, "    }}").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        /// Whether a field lives in the out-of-line region rather than inline.
+        /// Non-nullable identifiers are stored inline in FIDL, so only nullable
+        /// ones join vectors and strings out-of-line.
+        fn is_out_of_line(ty: &Type) -> bool {
+            matches!(
+                ty,
+                Type::Vector { .. }
+                    | Type::String { .. }
+                    | Type::Identifier { nullable: true, .. }
+            )
+        }
+
+        /// Emit the encode statement(s) for a single struct field.
+        fn emit_encode_field(out: &mut String, field: &str, ty: &Type, offset: u32) {
+            match ty {
+                Type::Primitive { subtype } if subtype == "bool" => {
+                    writeln!(
+                        out,
+                        "        buf[base + {0}..base + {0} + 1].copy_from_slice(&[self.{1} as u8]);",
+                        offset, field
+                    )
+                    .unwrap();
+                }
+                Type::Primitive { subtype } => {
+                    writeln!(
+                        out,
+                        "        buf[base + {0}..base + {0} + {1}]\
+                         .copy_from_slice(&self.{2}.to_le_bytes());",
+                        offset,
+                        primitive_size(subtype),
+                        field
+                    )
+                    .unwrap();
+                }
+                Type::String { .. } => {
+                    writeln!(out, "        {{").unwrap();
+                    writeln!(out, "            let bytes = self.{}.as_bytes();", field).unwrap();
+                    emit_ool_header(out, offset, "bytes.len()");
+                    writeln!(out, "            buf.extend_from_slice(bytes);").unwrap();
+                    emit_ool_pad(out);
+                    writeln!(out, "        }}").unwrap();
+                }
+                Type::Vector { element_type, .. } => {
+                    writeln!(out, "        {{").unwrap();
+                    writeln!(out, "            let items = &self.{};", field).unwrap();
+                    emit_ool_header(out, offset, "items.len()");
+                    writeln!(out, "            for item in items {{").unwrap();
+                    match element_type.as_ref() {
+                        Type::Primitive { .. } => writeln!(
+                            out,
+                            "                buf.extend_from_slice(&item.to_le_bytes());"
+                        )
+                        .unwrap(),
+                        _ => writeln!(out, "                item.encode(buf);").unwrap(),
+                    }
+                    writeln!(out, "            }}").unwrap();
+                    emit_ool_pad(out);
+                    writeln!(out, "        }}").unwrap();
+                }
+                Type::Identifier {
+                    identifier,
+                    nullable,
+                } => {
+                    let elem = local_name(identifier);
+                    if *nullable {
+                        writeln!(out, "        match &self.{} {{", field).unwrap();
+                        writeln!(out, "            Some(value) => {{").unwrap();
+                        writeln!(
+                            out,
+                            "                buf[base + {0}..base + {0} + 8]\
+                             .copy_from_slice(&u64::MAX.to_le_bytes());",
+                            offset
+                        )
+                        .unwrap();
+                        writeln!(out, "                value.encode(buf);").unwrap();
+                        writeln!(out, "            }}").unwrap();
+                        writeln!(out, "            None => {{").unwrap();
+                        writeln!(
+                            out,
+                            "                buf[base + {0}..base + {0} + 8]\
+                             .copy_from_slice(&0u64.to_le_bytes());",
+                            offset
+                        )
+                        .unwrap();
+                        writeln!(out, "            }}").unwrap();
+                        writeln!(out, "        }}").unwrap();
+                    } else {
+                        // Non-nullable: the nested value lives inline in the
+                        // field's reserved slot.
+                        writeln!(out, "        {{").unwrap();
+                        writeln!(out, "            let mut field_buf = Vec::new();").unwrap();
+                        writeln!(out, "            self.{}.encode(&mut field_buf);", field).unwrap();
+                        writeln!(
+                            out,
+                            "            buf[base + {0}..base + {0} + {1}::SIZE]\
+                             .copy_from_slice(&field_buf[..{1}::SIZE]);",
+                            offset, elem
+                        )
+                        .unwrap();
+                        writeln!(out, "        }}").unwrap();
+                    }
+                }
+                Type::Array {
+                    element_type,
+                    element_count,
+                } => {
+                    let esz = match element_type.as_ref() {
+                        Type::Primitive { subtype } => primitive_size(subtype),
+                        _ => 0,
+                    };
+                    writeln!(
+                        out,
+                        "        for (i, item) in self.{}.iter().enumerate() {{ // {} elements",
+                        field, element_count
+                    )
+                    .unwrap();
+                    writeln!(
+                        out,
+                        "            let at = base + {} + i * {};",
+                        offset, esz
+                    )
+                    .unwrap();
+                    writeln!(
+                        out,
+                        "            buf[at..at + {}].copy_from_slice(&item.to_le_bytes());",
+                        esz
+                    )
+                    .unwrap();
+                    writeln!(out, "        }}").unwrap();
+                }
+                Type::Handle { .. } | Type::Request { .. } => {
+                    writeln!(
+                        out,
+                        "        // handle `{}` travels in the handle table, not the byte buffer",
+                        field
+                    )
+                    .unwrap();
+                    writeln!(
+                        out,
+                        "        buf[base + {0}..base + {0} + 4].copy_from_slice(&0u32.to_le_bytes());",
+                        offset
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        /// Emit the decode statement binding a single struct field.
+        fn emit_decode_field(out: &mut String, field: &str, ty: &Type, offset: u32) {
+            match ty {
+                Type::Primitive { subtype } if subtype == "bool" => {
+                    writeln!(out, "        let {} = buf[{}] != 0;", field, offset).unwrap();
+                }
+                Type::Primitive { subtype } => {
+                    writeln!(
+                        out,
+                        "        let {0} = {1}::from_le_bytes(\
+                         buf[{2}..{2} + {3}].try_into().unwrap());",
+                        field,
+                        primitive_type(subtype),
+                        offset,
+                        primitive_size(subtype)
+                    )
+                    .unwrap();
+                }
+                Type::String { .. } => {
+                    writeln!(out, "        let {} = {{", field).unwrap();
+                    emit_ool_len(out, offset);
+                    writeln!(
+                        out,
+                        "            let value = String::from_utf8_lossy(&buf[ool..ool + len])\
+                         .into_owned();"
+                    )
+                    .unwrap();
+                    writeln!(out, "            ool += len;").unwrap();
+                    emit_ool_realign(out);
+                    writeln!(out, "            value").unwrap();
+                    writeln!(out, "        }};").unwrap();
+                }
+                Type::Vector { element_type, .. } => {
+                    writeln!(out, "        let {} = {{", field).unwrap();
+                    emit_ool_len(out, offset);
+                    writeln!(out, "            let mut items = Vec::with_capacity(len);").unwrap();
+                    writeln!(out, "            for _ in 0..len {{").unwrap();
+                    match element_type.as_ref() {
+                        Type::Primitive { subtype } => {
+                            let esz = primitive_size(subtype);
+                            writeln!(
+                                out,
+                                "                items.push({}::from_le_bytes(\
+                                 buf[ool..ool + {}].try_into().unwrap()));",
+                                primitive_type(subtype),
+                                esz
+                            )
+                            .unwrap();
+                            writeln!(out, "                ool += {};", esz).unwrap();
+                        }
+                        Type::Identifier { identifier, .. } => {
+                            let elem = local_name(identifier);
+                            writeln!(
+                                out,
+                                "                items.push({0}::decode(&buf[ool..ool + {0}::SIZE])?);",
+                                elem
+                            )
+                            .unwrap();
+                            writeln!(out, "                ool += {}::SIZE;", elem).unwrap();
+                        }
+                        _ => {
+                            writeln!(
+                                out,
+                                "                return Err(DecodeError::TrailingBytes); // unsupported element"
+                            )
+                            .unwrap();
+                        }
+                    }
+                    writeln!(out, "            }}").unwrap();
+                    emit_ool_realign(out);
+                    writeln!(out, "            items").unwrap();
+                    writeln!(out, "        }};").unwrap();
+                }
+                Type::Identifier {
+                    identifier,
+                    nullable,
+                } => {
+                    let elem = local_name(identifier);
+                    if *nullable {
+                        writeln!(out, "        let {} = {{", field).unwrap();
+                        writeln!(
+                            out,
+                            "            let present = u64::from_le_bytes(\
+                             buf[{0}..{0} + 8].try_into().unwrap());",
+                            offset
+                        )
+                        .unwrap();
+                        writeln!(out, "            if present != 0 {{").unwrap();
+                        writeln!(
+                            out,
+                            "                let value = {0}::decode(&buf[ool..ool + {0}::SIZE])?;",
+                            elem
+                        )
+                        .unwrap();
+                        writeln!(out, "                ool += {}::SIZE;", elem).unwrap();
+                        writeln!(out, "                Some(value)").unwrap();
+                        writeln!(out, "            }} else {{").unwrap();
+                        writeln!(out, "                None").unwrap();
+                        writeln!(out, "            }}").unwrap();
+                        writeln!(out, "        }};").unwrap();
+                    } else {
+                        // Non-nullable: read the nested value from its inline slot.
+                        writeln!(
+                            out,
+                            "        let {0} = {1}::decode(&buf[{2}..{2} + {1}::SIZE])?;",
+                            field, elem, offset
+                        )
+                        .unwrap();
+                    }
+                }
+                Type::Array { element_type, .. } => {
+                    let (rust, esz) = match element_type.as_ref() {
+                        Type::Primitive { subtype } => {
+                            (primitive_type(subtype), primitive_size(subtype))
+                        }
+                        _ => ("u8", 1),
+                    };
+                    writeln!(out, "        let {} = std::array::from_fn(|i| {{", field).unwrap();
+                    writeln!(out, "            let at = {} + i * {};", offset, esz).unwrap();
+                    writeln!(
+                        out,
+                        "            {}::from_le_bytes(buf[at..at + {}].try_into().unwrap())",
+                        rust, esz
+                    )
+                    .unwrap();
+                    writeln!(out, "        }});").unwrap();
+                }
+                Type::Handle { .. } | Type::Request { .. } => {
+                    writeln!(
+                        out,
+                        "        let {} = Default::default(); // handle supplied from the handle table",
+                        field
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        /// Emit the inline `(length, presence)` header for an out-of-line field.
+        fn emit_ool_header(out: &mut String, offset: u32, len_expr: &str) {
+            writeln!(
+                out,
+                "            buf[base + {0}..base + {0} + 8]\
+                 .copy_from_slice(&({1} as u64).to_le_bytes());",
+                offset, len_expr
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "            buf[base + {0} + 8..base + {0} + 16]\
+                 .copy_from_slice(&u64::MAX.to_le_bytes());",
+                offset
+            )
+            .unwrap();
+        }
+
+        /// Emit the trailing 8-byte alignment padding for an out-of-line block.
+        fn emit_ool_pad(out: &mut String) {
+            writeln!(out, "            while buf.len() % 8 != 0 {{").unwrap();
+            writeln!(out, "                buf.push(0);").unwrap();
+            writeln!(out, "            }}").unwrap();
+        }
+
+        /// Read the inline length word of an out-of-line field into `len`.
+        fn emit_ool_len(out: &mut String, offset: u32) {
+            writeln!(
+                out,
+                "            let len = u64::from_le_bytes(\
+                 buf[{0}..{0} + 8].try_into().unwrap()) as usize;",
+                offset
+            )
+            .unwrap();
+        }
+
+        /// Advance `ool` past the trailing 8-byte alignment padding on decode.
+        fn emit_ool_realign(out: &mut String) {
+            writeln!(out, "            while ool % 8 != 0 {{").unwrap();
+            writeln!(out, "                ool += 1;").unwrap();
+            writeln!(out, "            }}").unwrap();
+        }
+
+        /// Emit the encode of a union variant payload at the `ool` cursor.
+        fn emit_payload_encode(out: &mut String, ty: &Type, indent: &str) {
+            match ty {
+                Type::Primitive { subtype } if subtype == "bool" => {
+                    writeln!(out, "{}buf.push(*value as u8);", indent).unwrap();
+                    writeln!(out, "{}while buf.len() % 8 != 0 {{", indent).unwrap();
+                    writeln!(out, "{}    buf.push(0);", indent).unwrap();
+                    writeln!(out, "{}}}", indent).unwrap();
+                }
+                Type::Primitive { .. } => {
+                    writeln!(out, "{}buf.extend_from_slice(&value.to_le_bytes());", indent)
+                        .unwrap();
+                    writeln!(out, "{}while buf.len() % 8 != 0 {{", indent).unwrap();
+                    writeln!(out, "{}    buf.push(0);", indent).unwrap();
+                    writeln!(out, "{}}}", indent).unwrap();
+                }
+                _ => {
+                    writeln!(out, "{}value.encode(buf);", indent).unwrap();
+                }
+            }
+        }
+
+        /// Emit the decode of a union variant payload, binding `payload`.
+        fn emit_payload_decode(out: &mut String, ty: &Type, indent: &str) {
+            match ty {
+                Type::Primitive { subtype } if subtype == "bool" => {
+                    writeln!(out, "{}let payload = buf[ool] != 0;", indent).unwrap();
+                    writeln!(out, "{}ool += 1;", indent).unwrap();
+                    writeln!(out, "{}while ool % 8 != 0 {{", indent).unwrap();
+                    writeln!(out, "{}    ool += 1;", indent).unwrap();
+                    writeln!(out, "{}}}", indent).unwrap();
+                }
+                Type::Primitive { subtype } => {
+                    let esz = primitive_size(subtype);
+                    writeln!(
+                        out,
+                        "{}let payload = {}::from_le_bytes(buf[ool..ool + {}].try_into().unwrap());",
+                        indent,
+                        primitive_type(subtype),
+                        esz
+                    )
+                    .unwrap();
+                    writeln!(out, "{}ool += {};", indent, esz).unwrap();
+                    writeln!(out, "{}while ool % 8 != 0 {{", indent).unwrap();
+                    writeln!(out, "{}    ool += 1;", indent).unwrap();
+                    writeln!(out, "{}}}", indent).unwrap();
+                }
+                Type::Identifier { identifier, .. } => {
+                    let elem = local_name(identifier);
+                    writeln!(
+                        out,
+                        "{0}let payload = {1}::decode(&buf[ool..ool + {1}::SIZE])?;",
+                        indent, elem
+                    )
+                    .unwrap();
+                    writeln!(out, "{}ool += {}::SIZE;", indent, elem).unwrap();
+                }
+                _ => {
+                    writeln!(
+                        out,
+                        "{}return Err(DecodeError::TrailingBytes); // unsupported payload",
+                        indent
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        fn primitive_type(subtype: &str) -> &str {
+            super::codegen::primitive_type(subtype)
+        }
+
+        /// Wire size in bytes of a primitive subtype. Shared with the
+        /// `protocol` backend.
+        pub(super) fn primitive_size(subtype: &str) -> u32 {
+            match subtype {
+                "int8" | "uint8" | "bool" => 1,
+                "int16" | "uint16" => 2,
+                "int32" | "uint32" | "float32" => 4,
+                _ => 8,
+            }
+        }
+
+        fn local_name(name: &str) -> &str {
+            name.rsplit('/').next().unwrap_or(name)
+        }
+    }
+
+    pub mod protocol {
+        //! Generates the RPC glue for each [`Protocol`]: an async `FooProxy`
+        //! client and a `FooRequestStream`/`FooRequest` server side, keyed off
+        //! the method `ordinal`s the IR records.
+        use super::*;
+        use std::fmt::Write;
+
+        /// Emit proxy and request-stream code for every protocol in `lib`.
+        pub fn generate(lib: &Library) -> String {
+            let mut out = String::new();
+            for name in &lib.declaration_order {
+                if let Some(DeclarationType::Interface) = lib.declarations.get(name) {
+                    if let Some(p) = lib.interface_declarations.iter().find(|p| &p.name == name) {
+                        emit_proxy(&mut out, p);
+                        emit_request_stream(&mut out, p);
+                    }
+                }
+            }
+            out
+        }
+
+        fn emit_proxy(out: &mut String, p: &Protocol) {
+            let name = local_name(&p.name);
+            writeln!(out, "pub struct {}Proxy {{", name).unwrap();
+            writeln!(out, "    channel: Channel,").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+            writeln!(out, "impl {}Proxy {{", name).unwrap();
+            for m in &p.methods {
+                let args = request_args(m);
+                let ret = response_type(m);
+                writeln!(
+                    out,
+                    "    pub async fn {}(&self{}) -> Result<{}, Error> {{",
+                    m.name, args, ret
+                )
+                .unwrap();
+                writeln!(out, "        let mut buf = Vec::new();").unwrap();
+                writeln!(
+                    out,
+                    "        write_transaction_header(&mut buf, {});",
+                    m.ordinal
+                )
+                .unwrap();
+                for member in m.maybe_request.iter().flatten() {
+                    emit_arg_encode(out, &member.name, &member.r#type);
+                }
+                writeln!(out, "        self.channel.write(&buf)?;").unwrap();
+                if m.has_response {
+                    writeln!(out, "        let reply = self.channel.read().await?;").unwrap();
+                    writeln!(out, "        decode_response(&reply)").unwrap();
+                } else {
+                    // Fire-and-forget: nothing to await.
+                    writeln!(out, "        Ok(())").unwrap();
+                }
+                writeln!(out, "    }}").unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        fn emit_request_stream(out: &mut String, p: &Protocol) {
+            let name = local_name(&p.name);
+            writeln!(out, "pub enum {}Request {{", name).unwrap();
+            for m in &p.methods {
+                let mut fields = String::new();
+                for member in m.maybe_request.iter().flatten() {
+                    write!(fields, "{}: {}, ", member.name, rust_type(&member.r#type)).unwrap();
+                }
+                if m.has_response {
+                    write!(fields, "responder: {}{}Responder", name, m.name).unwrap();
+                }
+                writeln!(out, "    {} {{ {}}},", m.name, fields).unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+
+            // A responder per two-way method, carrying the channel back to the
+            // client and stamped with the method's ordinal on `send`.
+            for m in &p.methods {
+                if m.has_response {
+                    emit_responder(out, name, m);
+                }
+            }
+
+            writeln!(out, "pub struct {}RequestStream {{", name).unwrap();
+            writeln!(out, "    channel: Channel,").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+            writeln!(out, "impl {}RequestStream {{", name).unwrap();
+            writeln!(
+                out,
+                "    pub fn decode(&self, buf: &[u8]) -> Result<{}Request, Error> {{",
+                name
+            )
+            .unwrap();
+            writeln!(out, "        match read_ordinal(buf) {{").unwrap();
+            for m in &p.methods {
+                writeln!(out, "            {} => {{", m.ordinal).unwrap();
+                for member in m.maybe_request.iter().flatten() {
+                    emit_arg_decode(out, &member.name, &member.r#type, member.offset);
+                }
+                let mut fields = String::new();
+                for member in m.maybe_request.iter().flatten() {
+                    write!(fields, "{}, ", member.name).unwrap();
+                }
+                if m.has_response {
+                    write!(
+                        fields,
+                        "responder: {}{}Responder {{ channel: self.channel.clone() }}",
+                        name, m.name
+                    )
+                    .unwrap();
+                }
+                writeln!(
+                    out,
+                    "                Ok({}Request::{} {{ {}}})",
+                    name, m.name, fields
+                )
+                .unwrap();
+                writeln!(out, "            }}").unwrap();
+            }
+            writeln!(out, "            ordinal => Err(Error::UnknownOrdinal(ordinal)),").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(out, "    }}").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        /// Emit the responder struct and its `send` for a two-way method.
+        fn emit_responder(out: &mut String, name: &str, m: &ProtocolMethod) {
+            writeln!(out, "pub struct {}{}Responder {{", name, m.name).unwrap();
+            writeln!(out, "    channel: Channel,").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+            writeln!(out, "impl {}{}Responder {{", name, m.name).unwrap();
+            let mut args = String::new();
+            for member in m.maybe_response.iter().flatten() {
+                write!(args, ", {}: {}", member.name, rust_type(&member.r#type)).unwrap();
+            }
+            writeln!(
+                out,
+                "    pub fn send(self{}) -> Result<(), Error> {{",
+                args
+            )
+            .unwrap();
+            writeln!(out, "        let mut buf = Vec::new();").unwrap();
+            writeln!(
+                out,
+                "        write_transaction_header(&mut buf, {});",
+                m.ordinal
+            )
+            .unwrap();
+            for member in m.maybe_response.iter().flatten() {
+                emit_arg_encode(out, &member.name, &member.r#type);
+            }
+            writeln!(out, "        self.channel.write(&buf)").unwrap();
+            writeln!(out, "    }}").unwrap();
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        /// Serialize one request/response argument into `buf`.
+        fn emit_arg_encode(out: &mut String, field: &str, ty: &Type) {
+            match ty {
+                Type::Primitive { subtype } if subtype == "bool" => {
+                    writeln!(out, "        buf.push({} as u8);", field).unwrap();
+                }
+                Type::Primitive { .. } => {
+                    writeln!(out, "        buf.extend_from_slice(&{}.to_le_bytes());", field)
+                        .unwrap();
+                }
+                Type::String { .. } => {
+                    writeln!(out, "        buf.extend_from_slice({}.as_bytes());", field).unwrap();
+                }
+                Type::Vector { element_type, .. } => {
+                    writeln!(out, "        for item in &{} {{", field).unwrap();
+                    match element_type.as_ref() {
+                        Type::Primitive { .. } => {
+                            writeln!(out, "            buf.extend_from_slice(&item.to_le_bytes());")
+                                .unwrap()
+                        }
+                        _ => writeln!(out, "            item.encode(&mut buf);").unwrap(),
+                    }
+                    writeln!(out, "        }}").unwrap();
+                }
+                _ => {
+                    writeln!(out, "        {}.encode(&mut buf);", field).unwrap();
+                }
+            }
+        }
+
+        /// Bind one request argument of the method matched by ordinal.
+        /// Primitive arguments are read from `buf` at their offset; any other
+        /// argument is bound to `Default::default()` and not decoded.
+        fn emit_arg_decode(out: &mut String, field: &str, ty: &Type, offset: u32) {
+            match ty {
+                Type::Primitive { subtype } if subtype == "bool" => {
+                    writeln!(out, "                let {} = buf[{}] != 0;", field, offset).unwrap();
+                }
+                Type::Primitive { subtype } => {
+                    writeln!(
+                        out,
+                        "                let {0} = {1}::from_le_bytes(\
+                         buf[{2}..{2} + {3}].try_into().unwrap());",
+                        field,
+                        primitive_type(subtype),
+                        offset,
+                        primitive_size(subtype)
+                    )
+                    .unwrap();
+                }
+                _ => {
+                    writeln!(
+                        out,
+                        "                let {} = Default::default(); // out-of-line argument",
+                        field
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        /// Render the proxy method's argument list (leading `, ` included).
+        fn request_args(m: &ProtocolMethod) -> String {
+            let mut args = String::new();
+            for member in m.maybe_request.iter().flatten() {
+                write!(args, ", {}: {}", member.name, rust_type(&member.r#type)).unwrap();
+            }
+            args
+        }
+
+        /// Render the proxy method's success type from its response members.
+        fn response_type(m: &ProtocolMethod) -> String {
+            if !m.has_response {
+                return "()".to_owned();
+            }
+            let members: Vec<String> = m
+                .maybe_response
+                .iter()
+                .flatten()
+                .map(|member| rust_type(&member.r#type))
+                .collect();
+            match members.len() {
+                0 => "()".to_owned(),
+                1 => members.into_iter().next().unwrap(),
+                _ => format!("({})", members.join(", ")),
+            }
+        }
+
+        fn rust_type(ty: &Type) -> String {
+            match ty {
+                Type::Array {
+                    element_type,
+                    element_count,
+                } => format!("[{}; {}]", rust_type(element_type), element_count),
+                Type::Vector {
+                    element_type,
+                    nullable,
+                    ..
+                } => wrap_nullable(*nullable, format!("Vec<{}>", rust_type(element_type))),
+                Type::String { nullable, .. } => wrap_nullable(*nullable, "String".to_owned()),
+                Type::Handle { nullable, .. } => wrap_nullable(*nullable, "Handle".to_owned()),
+                Type::Request { nullable, .. } => wrap_nullable(*nullable, "ServerEnd".to_owned()),
+                Type::Primitive { subtype } => primitive_type(subtype).to_owned(),
+                Type::Identifier {
+                    identifier,
+                    nullable,
+                } => wrap_nullable(*nullable, local_name(identifier).to_owned()),
+            }
+        }
+
+        fn wrap_nullable(nullable: bool, inner: String) -> String {
+            if nullable {
+                format!("Option<{}>", inner)
+            } else {
+                inner
+            }
+        }
+
+        fn primitive_type(subtype: &str) -> &str {
+            super::codegen::primitive_type(subtype)
+        }
+
+        fn primitive_size(subtype: &str) -> u32 {
+            super::encoding::primitive_size(subtype)
+        }
+
+        fn local_name(name: &str) -> &str {
+            name.rsplit('/').next().unwrap_or(name)
+        }
+    }
+
+    pub mod codegen {
+        //! Walks a deserialized [`Library`] and emits Rust source for its
+        //! declarations. Output is produced in `declaration_order` so that a
+        //! declaration is always emitted after anything it refers to.
+        use super::*;
+        use std::fmt::Write;
+
+        /// Generate Rust bindings for every declaration in `lib`.
+        pub fn generate(lib: &Library) -> String {
+            let mut out = String::new();
+            let resolver = resolver::Resolver::new(lib);
+            for name in &lib.declaration_order {
+                match lib.declarations.get(name) {
+                    Some(DeclarationType::Const) => {
+                        if let Some(c) = lib.const_declarations.iter().find(|c| &c.name == name) {
+                            emit_const(&mut out, c, &resolver);
+                        }
+                    }
+                    Some(DeclarationType::Enum) => {
+                        if let Some(e) = lib.enum_declarations.iter().find(|e| &e.name == name) {
+                            emit_enum(&mut out, e);
+                        }
+                    }
+                    Some(DeclarationType::Struct) => {
+                        if let Some(s) = lib.struct_declarations.iter().find(|s| &s.name == name) {
+                            emit_struct(&mut out, s, &resolver);
+                        }
+                    }
+                    Some(DeclarationType::Union) | Some(DeclarationType::XUnion) => {
+                        if let Some(u) = lib.union_declarations.iter().find(|u| &u.name == name) {
+                            emit_union(&mut out, u, &resolver);
+                        }
+                    }
+                    Some(DeclarationType::Table) => {
+                        if let Some(t) = lib.table_declarations.iter().find(|t| &t.name == name) {
+                            emit_table(&mut out, t, &resolver);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            out
+        }
+
+        fn emit_const(out: &mut String, c: &Const, r: &resolver::Resolver) {
+            emit_doc(out, &c.maybe_attributes);
+            let ty = match &c.r#type {
+                Type::String { .. } => "&str".to_owned(),
+                other => rust_type(other, r),
+            };
+            writeln!(
+                out,
+                "pub const {}: {} = {};",
+                local_name(&c.name),
+                ty,
+                constant_expr(&c.value)
+            )
+            .unwrap();
+            out.push('\n');
+        }
+
+        fn emit_enum(out: &mut String, e: &Enum) {
+            emit_doc(out, &e.maybe_attributes);
+            writeln!(out, "#[repr({})]", primitive_type(&e.r#type)).unwrap();
+            writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]").unwrap();
+            writeln!(out, "pub enum {} {{", local_name(&e.name)).unwrap();
+            for m in &e.members {
+                // Prefer the normalized integer spelling of the discriminant,
+                // falling back to the raw constant for non-numeric values.
+                let discriminant = m
+                    .value
+                    .integer_value()
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|_| constant_expr(&m.value));
+                writeln!(out, "    {} = {},", m.name, discriminant).unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        fn emit_struct(out: &mut String, s: &Struct, r: &resolver::Resolver) {
+            emit_doc(out, &s.maybe_attributes);
+            writeln!(out, "#[derive(Debug, Clone)]").unwrap();
+            writeln!(out, "pub struct {} {{", local_name(&s.name)).unwrap();
+            for m in &s.members {
+                writeln!(out, "    pub {}: {},", m.name, rust_type(&m.r#type, r)).unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        fn emit_union(out: &mut String, u: &Union, r: &resolver::Resolver) {
+            emit_doc(out, &u.maybe_attributes);
+            writeln!(out, "#[derive(Debug, Clone)]").unwrap();
+            writeln!(out, "pub enum {} {{", local_name(&u.name)).unwrap();
+            for m in &u.members {
+                writeln!(out, "    {}({}),", m.name, rust_type(&m.r#type, r)).unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        fn emit_table(out: &mut String, t: &Table, r: &resolver::Resolver) {
+            emit_doc(out, &t.maybe_attributes);
+            writeln!(out, "#[derive(Debug, Clone)]").unwrap();
+            writeln!(out, "pub struct {} {{", local_name(&t.name)).unwrap();
+            for m in &t.members {
+                if m.reserved {
+                    continue;
+                }
+                if let (Some(name), Some(ty)) = (&m.name, &m.r#type) {
+                    writeln!(out, "    pub {}: Option<{}>,", name, rust_type(ty, r)).unwrap();
+                }
+            }
+            writeln!(out, "}}").unwrap();
+            out.push('\n');
+        }
+
+        /// Map a FIDL [`Type`] onto the Rust type used to hold it. Identifiers
+        /// are run through the [`resolver`] so cross-library references pick up
+        /// their canonical module path.
+        fn rust_type(ty: &Type, r: &resolver::Resolver) -> String {
+            match ty {
+                Type::Array {
+                    element_type,
+                    element_count,
+                } => format!("[{}; {}]", rust_type(element_type, r), element_count),
+                Type::Vector {
+                    element_type,
+                    nullable,
+                    ..
+                } => wrap_nullable(*nullable, format!("Vec<{}>", rust_type(element_type, r))),
+                Type::String { nullable, .. } => wrap_nullable(*nullable, "String".to_owned()),
+                Type::Handle { nullable, .. } => wrap_nullable(*nullable, "Handle".to_owned()),
+                Type::Request { nullable, .. } => {
+                    wrap_nullable(*nullable, "ServerEnd".to_owned())
+                }
+                Type::Primitive { subtype } => primitive_type(subtype).to_owned(),
+                Type::Identifier {
+                    identifier,
+                    nullable,
+                } => wrap_nullable(*nullable, r.rust_path(identifier)),
+            }
+        }
+
+        fn wrap_nullable(nullable: bool, inner: String) -> String {
+            if nullable {
+                format!("Option<{}>", inner)
+            } else {
+                inner
+            }
+        }
+
+        /// Map a FIDL primitive subtype name onto its Rust equivalent. Shared
+        /// with the `encoding` backend so the two never disagree on a field's
+        /// Rust type.
+        pub(super) fn primitive_type(subtype: &str) -> &str {
+            match subtype {
+                "int8" => "i8",
+                "int16" => "i16",
+                "int32" => "i32",
+                "int64" => "i64",
+                "uint8" => "u8",
+                "uint16" => "u16",
+                "uint32" => "u32",
+                "uint64" => "u64",
+                "float32" => "f32",
+                "float64" => "f64",
+                "bool" => "bool",
+                other => other,
+            }
+        }
+
+        /// Render a [`Constant`] as a Rust literal expression.
+        fn constant_expr(c: &Constant) -> String {
+            match c {
+                Constant::Identifier { identifier } => local_name(identifier).to_owned(),
+                Constant::Literal { literal } => match literal {
+                    Literal::String { value } => format!("{:?}", value),
+                    Literal::Numeric { value } => value.clone(),
+                    Literal::True {} => "true".to_owned(),
+                    Literal::False {} => "false".to_owned(),
+                    Literal::Default {} => "Default::default()".to_owned(),
+                },
+            }
+        }
+
+        /// Emit a `///` doc comment from the declaration's `@doc` attribute,
+        /// if any.
+        fn emit_doc(out: &mut String, attrs: &Option<Vec<Attribute>>) {
+            if let Some(lines) = get_attribute(attrs, "doc") {
+                for line in lines {
+                    for line in line.lines() {
+                        writeln!(out, "/// {}", line.trim()).unwrap();
+                    }
+                }
+            }
+        }
+
+        /// Strip any library qualifier, returning the bare declaration name.
+        fn local_name(name: &str) -> &str {
+            name.rsplit('/').next().unwrap_or(name)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// A tiny library exercising a const, an enum and a two-field struct.
+        const FIXTURE: &str = r#"{
+            "version": "0.0.1",
+            "name": "example",
+            "const_declarations": [
+                {
+                    "name": "example/SEVEN",
+                    "type": { "kind": "primitive", "subtype": "uint32" },
+                    "value": { "kind": "literal", "literal": { "kind": "numeric", "value": "7" } }
+                }
+            ],
+            "enum_declarations": [
+                {
+                    "name": "example/Color",
+                    "type": "uint32",
+                    "members": [
+                        { "name": "Red", "value": { "kind": "literal", "literal": { "kind": "numeric", "value": "0" } } },
+                        { "name": "Green", "value": { "kind": "literal", "literal": { "kind": "numeric", "value": "1" } } }
+                    ]
+                }
+            ],
+            "interface_declarations": [],
+            "struct_declarations": [
+                {
+                    "name": "example/Point",
+                    "members": [
+                        { "name": "x", "type": { "kind": "primitive", "subtype": "uint32" }, "size": 4, "max_out_of_line": 0, "alignment": 4, "offset": 0 },
+                        { "name": "y", "type": { "kind": "primitive", "subtype": "uint32" }, "size": 4, "max_out_of_line": 0, "alignment": 4, "offset": 4 }
+                    ],
+                    "size": 8,
+                    "max_out_of_line": 0
+                }
+            ],
+            "table_declarations": [],
+            "union_declarations": [],
+            "xunion_declarations": [],
+            "declaration_order": ["example/SEVEN", "example/Color", "example/Point"],
+            "declarations": {
+                "example/SEVEN": "const",
+                "example/Color": "enum",
+                "example/Point": "struct"
+            },
+            "library_dependencies": []
+        }"#;
+
+        fn fixture() -> Library {
+            serde_json::from_value(migrate(serde_json::from_str(FIXTURE).unwrap())).unwrap()
+        }
+
+        #[test]
+        fn codegen_matches_golden() {
+            let golden = "\
+pub const SEVEN: u32 = 7;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red = 0,
+    Green = 1,
+}
+
+#[derive(Debug, Clone)]
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+";
+            assert_eq!(codegen::generate(&fixture()), golden);
+        }
+
+        #[test]
+        fn encoding_emits_struct_codec() {
+            let output = encoding::generate(&fixture());
+            assert!(output.contains("pub enum DecodeError {"));
+            assert!(output.contains("impl Point {"));
+            assert!(output.contains("pub const SIZE: usize = 8;"));
+            assert!(output
+                .contains("buf[base + 0..base + 0 + 4].copy_from_slice(&self.x.to_le_bytes());"));
+            assert!(output
+                .contains("let y = u32::from_le_bytes(buf[4..4 + 4].try_into().unwrap());"));
+            assert!(output.contains("return Err(DecodeError::TrailingBytes);"));
+        }
     }
 
 }
@@ -253,6 +1784,8 @@ mod ir {
 fn main() {
     for f in env::args().skip(1) {
         let lib = ir::read_ir(&f);
-        println!("{:#?}", lib);
+        print!("{}", ir::codegen::generate(&lib));
+        print!("{}", ir::encoding::generate(&lib));
+        print!("{}", ir::protocol::generate(&lib));
     }
 }